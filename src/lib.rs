@@ -3,6 +3,14 @@ extern crate failure;
 extern crate byteorder;
 #[cfg(test)]
 extern crate glob;
+#[cfg(feature = "compress-zip")]
+extern crate zip;
+#[cfg(feature = "compress-gzip")]
+extern crate flate2;
+#[cfg(feature = "compress-zstd")]
+extern crate zstd;
+#[cfg(feature = "compress-lzma")]
+extern crate xz2;
 
 pub mod cpu;
 pub mod errors;
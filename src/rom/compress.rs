@@ -0,0 +1,226 @@
+//! Transparent decompression of compressed ROM containers.
+use std::io::{Read, Seek, SeekFrom};
+
+use errors::Result;
+use rom::{self, ROM};
+#[cfg(feature = "compress-zip")]
+use rom::Endian;
+
+/// File extensions recognized as ROM dumps when picking an entry out of
+/// an archive.
+#[cfg(feature = "compress-zip")]
+const ROM_EXTENSIONS: [&str; 3] = ["z64", "v64", "n64"];
+
+const ZIP_MAGIC: [u8; 4] = [0x50, 0x4B, 0x03, 0x04];
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+const XZ_MAGIC: [u8; 5] = [0xFD, 0x37, 0x7A, 0x58, 0x5A];
+
+/// Parses a ROM, transparently decompressing `.zip`, gzip, zstd, or
+/// xz/LZMA containers first. Falls back to the raw-ROM path when the
+/// leading bytes don't match a known compression magic.
+pub fn parse_any<R: Read + Seek>(mut reader: R) -> Result<ROM> {
+    let mut magic = [0u8; 6];
+    let read = read_some(&mut reader, &mut magic)?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    let magic = &magic[..read];
+    if magic.starts_with(&ZIP_MAGIC) {
+        return parse_zip(reader);
+    }
+    if magic.starts_with(&GZIP_MAGIC) {
+        return parse_gzip(reader);
+    }
+    if magic.starts_with(&ZSTD_MAGIC) {
+        return parse_zstd(reader);
+    }
+    if magic.starts_with(&XZ_MAGIC) {
+        return parse_xz(reader);
+    }
+
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    rom::parse(data)
+}
+
+/// Like `Read::read`, but keeps pulling until `buf` is full or the
+/// stream is exhausted, since a single `read` call may return short.
+fn read_some<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut total = 0;
+    while total < buf.len() {
+        match reader.read(&mut buf[total..])? {
+            0 => break,
+            n => total += n,
+        }
+    }
+    Ok(total)
+}
+
+#[cfg(feature = "compress-zip")]
+fn parse_zip<R: Read + Seek>(reader: R) -> Result<ROM> {
+    let mut archive = ::zip::ZipArchive::new(reader)?;
+
+    // Prefer an entry whose name looks like a ROM dump...
+    for i in 0..archive.len() {
+        let name = archive.by_index(i)?.name().to_ascii_lowercase();
+        if ROM_EXTENSIONS.iter().any(|ext| name.ends_with(ext)) {
+            let mut data = Vec::new();
+            archive.by_index(i)?.read_to_end(&mut data)?;
+            return rom::parse(data);
+        }
+    }
+
+    // ...and fall back to sniffing each entry's leading byte for a ROM
+    // header magic, for archives with no helpful naming.
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let mut first = [0u8; 1];
+        if file.read_exact(&mut first).is_ok() && Endian::from_u8(first[0]).is_some() {
+            let mut data = first.to_vec();
+            file.read_to_end(&mut data)?;
+            return rom::parse(data);
+        }
+    }
+
+    Err(format_err!(
+        "zip archive does not contain a recognizable ROM entry"
+    ))
+}
+
+#[cfg(not(feature = "compress-zip"))]
+fn parse_zip<R: Read + Seek>(_reader: R) -> Result<ROM> {
+    Err(format_err!(
+        "zip-compressed ROM detected, but the `compress-zip` feature is not enabled"
+    ))
+}
+
+#[cfg(feature = "compress-gzip")]
+fn parse_gzip<R: Read>(reader: R) -> Result<ROM> {
+    let mut decoder = ::flate2::read::GzDecoder::new(reader);
+    let mut data = Vec::new();
+    decoder.read_to_end(&mut data)?;
+    rom::parse(data)
+}
+
+#[cfg(not(feature = "compress-gzip"))]
+fn parse_gzip<R: Read>(_reader: R) -> Result<ROM> {
+    Err(format_err!(
+        "gzip-compressed ROM detected, but the `compress-gzip` feature is not enabled"
+    ))
+}
+
+#[cfg(feature = "compress-zstd")]
+fn parse_zstd<R: Read>(reader: R) -> Result<ROM> {
+    let mut decoder = ::zstd::stream::read::Decoder::new(reader)?;
+    let mut data = Vec::new();
+    decoder.read_to_end(&mut data)?;
+    rom::parse(data)
+}
+
+#[cfg(not(feature = "compress-zstd"))]
+fn parse_zstd<R: Read>(_reader: R) -> Result<ROM> {
+    Err(format_err!(
+        "zstd-compressed ROM detected, but the `compress-zstd` feature is not enabled"
+    ))
+}
+
+#[cfg(feature = "compress-lzma")]
+fn parse_xz<R: Read>(reader: R) -> Result<ROM> {
+    let mut decoder = ::xz2::read::XzDecoder::new(reader);
+    let mut data = Vec::new();
+    decoder.read_to_end(&mut data)?;
+    rom::parse(data)
+}
+
+#[cfg(not(feature = "compress-lzma"))]
+fn parse_xz<R: Read>(_reader: R) -> Result<ROM> {
+    Err(format_err!(
+        "xz/LZMA-compressed ROM detected, but the `compress-lzma` feature is not enabled"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rom::{HEADER_NATIVE, HEADER_SIZE};
+    use std::io::Cursor;
+
+    fn native_rom_bytes(body_len: usize) -> Vec<u8> {
+        let mut data = vec![0u8; HEADER_SIZE];
+        data[0..4].copy_from_slice(&HEADER_NATIVE);
+        data.extend((0..body_len as u32).map(|i| (i % 256) as u8));
+        data
+    }
+
+    #[test]
+    fn test_parse_any_falls_back_to_raw_rom() {
+        let native = native_rom_bytes(64);
+        let rom = parse_any(Cursor::new(native.clone())).unwrap();
+        assert_eq!(rom.data, rom::parse(native).unwrap().data);
+    }
+
+    #[test]
+    fn test_parse_any_gzip_magic_errors_cleanly_without_panic() {
+        let mut data = GZIP_MAGIC.to_vec();
+        data.extend_from_slice(b"not actually gzip data");
+        assert!(parse_any(Cursor::new(data)).is_err());
+    }
+
+    #[test]
+    fn test_parse_any_zstd_magic_errors_cleanly_without_panic() {
+        let mut data = ZSTD_MAGIC.to_vec();
+        data.extend_from_slice(b"not actually zstd data");
+        assert!(parse_any(Cursor::new(data)).is_err());
+    }
+
+    #[test]
+    fn test_parse_any_xz_magic_errors_cleanly_without_panic() {
+        let mut data = XZ_MAGIC.to_vec();
+        data.extend_from_slice(b"not actually xz data");
+        assert!(parse_any(Cursor::new(data)).is_err());
+    }
+
+    #[cfg(feature = "compress-zip")]
+    #[test]
+    fn test_parse_any_zip_picks_rom_entry_by_name() {
+        use std::io::Write;
+
+        let native = native_rom_bytes(64);
+
+        let mut zip_bytes = Cursor::new(Vec::new());
+        {
+            let mut writer = ::zip::ZipWriter::new(&mut zip_bytes);
+            let options = ::zip::write::FileOptions::default();
+            writer.start_file("README.txt", options).unwrap();
+            writer.write_all(b"just a readme, not a ROM").unwrap();
+            writer.start_file("game.z64", options).unwrap();
+            writer.write_all(&native).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let rom = parse_any(Cursor::new(zip_bytes.into_inner())).unwrap();
+        assert_eq!(rom.data, rom::parse(native).unwrap().data);
+    }
+
+    #[cfg(feature = "compress-zip")]
+    #[test]
+    fn test_parse_any_zip_picks_rom_entry_by_magic_when_unnamed() {
+        use std::io::Write;
+
+        let native = native_rom_bytes(64);
+
+        let mut zip_bytes = Cursor::new(Vec::new());
+        {
+            let mut writer = ::zip::ZipWriter::new(&mut zip_bytes);
+            let options = ::zip::write::FileOptions::default();
+            writer.start_file("readme", options).unwrap();
+            writer.write_all(b"just a readme, not a ROM").unwrap();
+            writer.start_file("dump.bin", options).unwrap();
+            writer.write_all(&native).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let rom = parse_any(Cursor::new(zip_bytes.into_inner())).unwrap();
+        assert_eq!(rom.data, rom::parse(native).unwrap().data);
+    }
+}
@@ -0,0 +1,138 @@
+//! The 0x1000-byte N64 ROM header.
+use byteorder::{BigEndian, ByteOrder};
+use std::ops::Range;
+
+use errors::Result;
+use rom::HEADER_SIZE;
+
+/// Represents an in memory version of a parse ROM header.
+// TODO: fixed size array on the heap?
+#[derive(Debug, Clone)]
+pub struct InternalHeader {
+    pub(crate) data: Vec<u8>,
+}
+
+impl InternalHeader {
+    pub fn new(data: Vec<u8>) -> Result<InternalHeader> {
+        if data.len() != HEADER_SIZE {
+            Err(format_err!(
+                "invalid header size: {:#x} != {:#x}",
+                data.len(),
+                HEADER_SIZE
+            ))
+        } else {
+            Ok(InternalHeader { data })
+        }
+    }
+
+    /// Checked access to a range of the header, naming the field and
+    /// offset in the error when the header has been truncated.
+    fn field(&self, range: Range<usize>, name: &'static str) -> Result<&[u8]> {
+        self.data.get(range.clone()).ok_or_else(|| {
+            format_err!(
+                "{}: not enough header data at {:#x}..{:#x}",
+                name,
+                range.start,
+                range.end
+            )
+        })
+    }
+
+    fn field_u8(&self, offset: usize, name: &'static str) -> Result<u8> {
+        self.field(offset..offset + 1, name).map(|s| s[0])
+    }
+
+    fn field_u16(&self, range: Range<usize>, name: &'static str) -> Result<u16> {
+        self.field(range, name).map(BigEndian::read_u16)
+    }
+
+    fn field_u32(&self, range: Range<usize>, name: &'static str) -> Result<u32> {
+        self.field(range, name).map(BigEndian::read_u32)
+    }
+
+    pub fn pi_bsb_dom1_lat_reg(&self) -> Result<u8> {
+        self.field_u8(0, "pi_bsb_dom1_lat_reg")
+    }
+
+    pub fn pi_bsd_dom1_pgs_reg(&self) -> Result<u8> {
+        self.field_u8(1, "pi_bsd_dom1_pgs_reg")
+    }
+
+    pub fn pi_bsd_dom1_pwd_reg(&self) -> Result<u8> {
+        self.field_u8(2, "pi_bsd_dom1_pwd_reg")
+    }
+
+    pub fn pi_bsb_dom1_pgs_reg(&self) -> Result<u8> {
+        self.field_u8(3, "pi_bsb_dom1_pgs_reg")
+    }
+
+    /// 0004h - 0007h     (1 dword): ClockRate
+    pub fn clock_rate(&self) -> Result<u32> {
+        self.field_u32(0x4..0x8, "clock_rate")
+    }
+
+    /// 0008h - 000Bh     (1 dword): Program Counter (PC)
+    pub fn pc(&self) -> Result<u32> {
+        self.field_u32(0x8..0xC, "pc")
+    }
+
+    /// 000Ch - 000Fh     (1 dword): Release
+    pub fn release(&self) -> Result<u32> {
+        self.field_u32(0xC..0x10, "release")
+    }
+
+    /// 0010h - 0013h     (1 dword): CRC1
+    pub fn crc1(&self) -> Result<u32> {
+        self.field_u32(0x10..0x14, "crc1")
+    }
+
+    /// 0014h - 0017h     (1 dword): CRC2
+    pub fn crc2(&self) -> Result<u32> {
+        self.field_u32(0x14..0x18, "crc2")
+    }
+
+    /// 0018h - 001Fh    (2 dwords): Unknown (0x0000000000000000)
+    pub fn unknown_1(&self) -> Result<[u32; 2]> {
+        Ok([
+            self.field_u32(0x18..0x1C, "unknown_1[0]")?,
+            self.field_u32(0x1C..0x20, "unknown_1[1]")?,
+        ])
+    }
+
+    /// 0020h - 0033h    (20 bytes): Image name
+    ///                              Padded with 0x00 or spaces (0x20)
+    pub fn image_name(&self) -> Result<&[u8]> {
+        self.field(0x20..0x34, "image_name")
+    }
+
+    /// 0034h - 0037h     (1 dword): Unknown (0x00000000)
+    pub fn unknown_2(&self) -> Result<u32> {
+        self.field_u32(0x34..0x38, "unknown_2")
+    }
+
+    /// 0038h - 003Bh     (1 dword): Manufacturer ID
+    ///                              0x0000004E = Nintendo ('N')
+    pub fn manufactorer_id(&self) -> Result<u32> {
+        self.field_u32(0x38..0x3C, "manufactorer_id")
+    }
+
+    /// 003Ch - 003Dh      (1 word): Cartridge ID
+    pub fn cartridge_id(&self) -> Result<u16> {
+        self.field_u16(0x3C..0x3E, "cartridge_id")
+    }
+
+    /// 003Eh - 003Fh      (1 word): Country code
+    ///                              0x4400 = Germany ('D')
+    ///                              0x4500 = USA ('E')
+    ///                              0x4A00 = Japan ('J')
+    ///                              0x5000 = Europe ('P')
+    ///                              0x5500 = Australia ('U')
+    pub fn country_code(&self) -> Result<u16> {
+        self.field_u16(0x3E..0x40, "country_code")
+    }
+
+    /// 0040h - 0FFFh (1008 dwords): Boot code
+    pub fn boot_code(&self) -> Result<&[u8]> {
+        self.field(0x40..0x1000, "boot_code")
+    }
+}
@@ -0,0 +1,184 @@
+//! Typed region, maker, and title metadata decoded from the header, the
+//! natural key for looking a ROM up in a redump/no-intro database.
+use std::str;
+
+use errors::Result;
+use rom::InternalHeader;
+
+/// The region a ROM was built for (`InternalHeader::country_code`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    Germany,
+    Usa,
+    Japan,
+    Europe,
+    Australia,
+    Unknown(u8),
+}
+
+impl Region {
+    fn from_byte(b: u8) -> Region {
+        match b {
+            b'D' => Region::Germany,
+            b'E' => Region::Usa,
+            b'J' => Region::Japan,
+            b'P' => Region::Europe,
+            b'U' => Region::Australia,
+            other => Region::Unknown(other),
+        }
+    }
+}
+
+/// The manufacturer of a cartridge (`InternalHeader::manufactorer_id`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Maker {
+    Nintendo,
+    Unknown(u8),
+}
+
+impl Maker {
+    fn from_byte(b: u8) -> Maker {
+        match b {
+            b'N' => Maker::Nintendo,
+            other => Maker::Unknown(other),
+        }
+    }
+}
+
+/// A structured view over `InternalHeader`'s identification fields.
+pub struct RomMeta<'a> {
+    header: &'a InternalHeader,
+}
+
+impl<'a> RomMeta<'a> {
+    pub fn new(header: &'a InternalHeader) -> RomMeta<'a> {
+        RomMeta { header }
+    }
+
+    /// The region this ROM was built for.
+    pub fn region(&self) -> Result<Region> {
+        let code = self.header.country_code()?;
+        Ok(Region::from_byte((code >> 8) as u8))
+    }
+
+    /// The manufacturer of the cartridge.
+    pub fn maker(&self) -> Result<Maker> {
+        let id = self.header.manufactorer_id()?;
+        Ok(Maker::from_byte(id as u8))
+    }
+
+    /// The two-character game code, e.g. "SM" for Super Mario 64.
+    pub fn cartridge_id(&self) -> Result<&'a str> {
+        let bytes = self
+            .header
+            .data
+            .get(0x3C..0x3E)
+            .ok_or_else(|| format_err!("cartridge_id: not enough header data"))?;
+        str::from_utf8(bytes).map_err(|e| format_err!("cartridge_id: invalid utf8: {}", e))
+    }
+
+    /// The image name, trimmed of its trailing 0x00/space padding and
+    /// decoded as UTF-8, falling back to Latin-1 for non-UTF-8 titles.
+    pub fn image_name(&self) -> Result<String> {
+        let raw = self.header.image_name()?;
+        let trimmed = trim_padding(raw);
+        Ok(match str::from_utf8(trimmed) {
+            Ok(s) => s.to_string(),
+            Err(_) => trimmed.iter().map(|&b| b as char).collect(),
+        })
+    }
+}
+
+/// Strips trailing 0x00/space padding off a fixed-width header field.
+fn trim_padding(raw: &[u8]) -> &[u8] {
+    let end = raw
+        .iter()
+        .rposition(|&b| b != 0x00 && b != b' ')
+        .map_or(0, |i| i + 1);
+    &raw[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_region_from_byte() {
+        assert_eq!(Region::from_byte(b'D'), Region::Germany);
+        assert_eq!(Region::from_byte(b'E'), Region::Usa);
+        assert_eq!(Region::from_byte(b'J'), Region::Japan);
+        assert_eq!(Region::from_byte(b'P'), Region::Europe);
+        assert_eq!(Region::from_byte(b'U'), Region::Australia);
+        assert_eq!(Region::from_byte(b'X'), Region::Unknown(b'X'));
+    }
+
+    #[test]
+    fn test_maker_from_byte() {
+        assert_eq!(Maker::from_byte(b'N'), Maker::Nintendo);
+        assert_eq!(Maker::from_byte(b'X'), Maker::Unknown(b'X'));
+    }
+
+    #[test]
+    fn test_trim_padding_strips_trailing_nulls_and_spaces() {
+        assert_eq!(trim_padding(b"SUPER MARIO 64\0\0\0\0\0\0"), b"SUPER MARIO 64");
+        assert_eq!(trim_padding(b"GAME  \0\0"), b"GAME");
+    }
+
+    #[test]
+    fn test_trim_padding_all_padding_is_empty() {
+        assert_eq!(trim_padding(&[0x00; 8]), &[] as &[u8]);
+        assert_eq!(trim_padding(b"        "), &[] as &[u8]);
+    }
+
+    #[test]
+    fn test_trim_padding_no_padding_is_unchanged() {
+        assert_eq!(trim_padding(b"FULLNAME"), b"FULLNAME");
+    }
+
+    /// Builds a minimal header with the identification fields set and
+    /// everything else zeroed.
+    fn build_header(country: u8, maker: u8, cart_id: [u8; 2], image_name: &[u8; 20]) -> InternalHeader {
+        let mut data = vec![0u8; 0x1000];
+        data[0x20..0x34].copy_from_slice(image_name);
+        data[0x38..0x3C].copy_from_slice(&[0, 0, 0, maker]);
+        data[0x3C..0x3E].copy_from_slice(&cart_id);
+        data[0x3E..0x40].copy_from_slice(&[country, 0x00]);
+        InternalHeader::new(data).unwrap()
+    }
+
+    #[test]
+    fn test_meta_region_maker_cartridge_id() {
+        let header = build_header(b'E', b'N', *b"SM", b"SUPER MARIO 64      ");
+        let meta = RomMeta::new(&header);
+
+        assert_eq!(meta.region().unwrap(), Region::Usa);
+        assert_eq!(meta.maker().unwrap(), Maker::Nintendo);
+        assert_eq!(meta.cartridge_id().unwrap(), "SM");
+        assert_eq!(meta.image_name().unwrap(), "SUPER MARIO 64");
+    }
+
+    #[test]
+    fn test_meta_image_name_latin1_fallback_on_invalid_utf8() {
+        // 0xE9 alone is not valid UTF-8, so this must fall back to
+        // decoding each byte as a Latin-1 code point instead of erroring.
+        let mut image_name = [0x00; 20];
+        image_name[0] = b'R';
+        image_name[1] = 0xE9; // 'é' in Latin-1
+        image_name[2] = b'X';
+
+        let header = build_header(b'J', b'N', *b"RX", &image_name);
+        let meta = RomMeta::new(&header);
+
+        assert_eq!(meta.image_name().unwrap(), "R\u{e9}X");
+    }
+
+    #[test]
+    fn test_meta_unknown_region_and_maker() {
+        let header = build_header(b'Z', b'Z', *b"ZZ", b"                    ");
+        let meta = RomMeta::new(&header);
+
+        assert_eq!(meta.region().unwrap(), Region::Unknown(b'Z'));
+        assert_eq!(meta.maker().unwrap(), Maker::Unknown(b'Z'));
+        assert_eq!(meta.image_name().unwrap(), "");
+    }
+}
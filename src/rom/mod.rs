@@ -1,5 +1,16 @@
-use byteorder::{BigEndian, ReadBytesExt};
 use errors::Result;
+use std::io::{Cursor, Write};
+
+mod checksum;
+mod compress;
+mod header;
+mod meta;
+mod reader;
+pub use self::checksum::Cic;
+pub use self::compress::parse_any;
+pub use self::header::InternalHeader;
+pub use self::meta::{Maker, Region, RomMeta};
+pub use self::reader::RomReader;
 
 const HEADER_SIZE: usize = 0x1000;
 
@@ -38,154 +49,100 @@ pub struct ROM {
     pub data: Vec<u8>,
 }
 
-/// Represents an in memory version of a parse ROM header.
-// TODO: fixed size array on the heap?
-#[derive(Debug)]
-pub struct InternalHeader {
-    data: Vec<u8>,
-}
-
-impl InternalHeader {
-    pub fn new(data: Vec<u8>) -> Result<InternalHeader> {
-        if data.len() != HEADER_SIZE {
-            return Err(format_err!(
-                "invalid header size: {:#x} != {:#x}",
-                data.len(),
-                HEADER_SIZE
-            ));
-        } else {
-            Ok(InternalHeader { data })
-        }
-    }
-
-    pub fn pi_bsb_dom1_lat_reg(&self) -> u8 {
-        self.data[0]
+impl ROM {
+    /// Detects the CIC boot chip this ROM was built for, by matching the
+    /// CRC32 of its boot code against the known chips.
+    pub fn detect_cic(&self) -> Result<Cic> {
+        Cic::detect(self.header.boot_code()?)
     }
 
-    pub fn pi_bsd_dom1_pgs_reg(&self) -> u8 {
-        self.data[1]
+    /// Recomputes CRC1/CRC2 the way the N64 bootcode does, returning
+    /// `(crc1, crc2)`. Requires the ROM body to be at least 1 MiB.
+    pub fn recompute_checksum(&self) -> Result<(u32, u32)> {
+        let cic = self.detect_cic()?;
+        checksum::compute_crc(cic, self.header.boot_code()?, &self.data)
     }
 
-    pub fn pi_bsd_dom1_pwd_reg(&self) -> u8 {
-        self.data[2]
+    /// Recomputes CRC1/CRC2 and checks them against the values stored in
+    /// the header, to catch corrupt or hand-edited ROMs.
+    pub fn verify_checksum(&self) -> Result<bool> {
+        let (crc1, crc2) = self.recompute_checksum()?;
+        Ok(crc1 == self.header.crc1()? && crc2 == self.header.crc2()?)
     }
 
-    pub fn pi_bsb_dom1_pgs_reg(&self) -> u8 {
-        self.data[3]
+    /// Re-emits this ROM's header and body in `target` byte order, e.g. to
+    /// convert a native big-endian `.z64` into a byte-swapped `.v64` or a
+    /// little-endian `.n64` dump.
+    pub fn write<W: Write>(&self, w: &mut W, target: Endian) -> Result<()> {
+        let mut buf = Vec::with_capacity(self.header.data.len() + self.data.len());
+        buf.extend_from_slice(&self.header.data);
+        buf.extend_from_slice(&self.data);
+        apply_endian(&mut buf, target);
+        w.write_all(&buf)?;
+        Ok(())
     }
 
-    /// 0004h - 0007h     (1 dword): ClockRate
-    pub fn clock_rate(&self) -> u64 {
-        read_u64(&self.data[0x4..0x8])
+    /// Convenience wrapper around `write` that returns the re-emitted
+    /// bytes instead of writing them to a stream.
+    pub fn to_vec(&self, target: Endian) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.write(&mut buf, target)?;
+        Ok(buf)
     }
 
-    /// 0008h - 000Bh     (1 dword): Program Counter (PC)
-    pub fn pc(&self) -> u64 {
-        read_u64(&self.data[0x8..0xC])
-    }
-
-    /// 000Ch - 000Fh     (1 dword): Release
-    pub fn release(&self) -> u64 {
-        read_u64(&self.data[0x8..0xC])
-    }
-
-    /// 0010h - 0013h     (1 dword): CRC1
-    pub fn crc1(&self) -> u64 {
-        read_u64(&self.data[0x10..0x14])
-    }
-
-    /// 0014h - 0017h     (1 dword): CRC2
-    pub fn crc2(&self) -> u64 {
-        read_u64(&self.data[0x14..0x18])
-    }
-
-    /// 0018h - 001Fh    (2 dwords): Unknown (0x0000000000000000)
-    pub fn unknown_1(&self) -> [u64; 2] {
-        [
-            read_u64(&self.data[0x18..0x1C]),
-            read_u64(&self.data[0x1C..0x20]),
-        ]
-    }
-
-    /// 0020h - 0033h    (20 bytes): Image name
-    ///                              Padded with 0x00 or spaces (0x20)
-    pub fn image_name(&self) -> &[u8] {
-        &self.data[0x20..0x33]
-    }
-
-    /// 0034h - 0037h     (1 dword): Unknown (0x00000000)
-    pub fn unknown_2(&self) -> u64 {
-        read_u64(&self.data[0x34..0x38])
-    }
-
-    /// 0038h - 003Bh     (1 dword): Manufacturer ID
-    ///                              0x0000004E = Nintendo ('N')
-    pub fn manufactorer_id(&self) -> u64 {
-        // TODO: Enum
-        read_u64(&self.data[0x38..0x3C])
-    }
-
-    /// 003Ch - 003Dh      (1 word): Cartridge ID
-    pub fn cartridge_id(&self) -> u32 {
-        read_u32(&self.data[0x3C..0x3E])
-    }
-
-    /// 003Eh - 003Fh      (1 word): Country code
-    ///                              0x4400 = Germany ('D')
-    ///                              0x4500 = USA ('E')
-    ///                              0x4A00 = Japan ('J')
-    ///                              0x5000 = Europe ('P')
-    ///                              0x5500 = Australia ('U')
-    pub fn country_code(&self) -> u32 {
-        // TODO: enum
-        read_u32(&self.data[0x3E..0x40])
+    /// A structured view over this ROM's region/maker/title metadata.
+    pub fn meta(&self) -> RomMeta<'_> {
+        RomMeta::new(&self.header)
     }
+}
 
-    /// 0040h - 0FFFh (1008 dwords): Boot code
-    pub fn boot_code(&self) -> &[u8] {
-        &self.data[0x40..0x1000]
+/// Swaps each adjacent pair of bytes in place (the byte-swapped `.v64`
+/// format relative to native big-endian). Any odd trailing byte is left
+/// untouched rather than indexed out of bounds.
+fn swap_bytes_pairwise(data: &mut [u8]) {
+    let mut i = 0;
+    while i + 1 < data.len() {
+        data.swap(i, i + 1);
+        i += 2;
     }
 }
 
-fn read_u32<T: ReadBytesExt>(mut data: T) -> u32 {
-    data.read_u32::<BigEndian>().unwrap()
+/// Reverses each 4-byte word in place (the little-endian `.n64` format
+/// relative to native big-endian). Any trailing partial word is left
+/// untouched rather than indexed out of bounds.
+fn swap_words(data: &mut [u8]) {
+    let mut i = 0;
+    while i + 3 < data.len() {
+        data.swap(i, i + 3);
+        data.swap(i + 1, i + 2);
+        i += 4;
+    }
 }
 
-fn read_u64<T: ReadBytesExt>(mut data: T) -> u64 {
-    data.read_u64::<BigEndian>().unwrap()
+/// Converts `data` between native big-endian and `endian` in place. Every
+/// conversion here is its own inverse, so this is used both to bring a
+/// source ROM into native order and to re-emit a native ROM as `endian`.
+fn apply_endian(data: &mut [u8], endian: Endian) {
+    match endian {
+        Endian::Native => {}
+        Endian::ByteSwapped => swap_bytes_pairwise(data),
+        Endian::Little => swap_words(data),
+    }
 }
 
-/// Parses a full ROM.
+/// Parses a full ROM, reading it all into memory through a `RomReader`.
 pub fn parse(data: Vec<u8>) -> Result<ROM> {
-    let mut data = data;
-    match Endian::from_u8(data[0]) {
-        Some(e) => {
-            match e {
-                // Nothing to do, all good
-                Endian::Native => {}
-                Endian::ByteSwapped => {
-                    // swap bytes
-                    let mut i = 0;
-                    while i < data.len() {
-                        data.swap(i, i + 1);
-                        i += 2;
-                    }
-                }
-                Endian::Little => {
-                    // convert to big endian
-                    data.reverse();
-                }
-            }
-        }
-        None => return Err(format_err!("unknown header: {:#x}", data[0])),
-    }
+    let len = data.len();
+    let body_len = match len.checked_sub(HEADER_SIZE) {
+        Some(n) => n,
+        None => return Err(format_err!("data too short for header: {:#x} < {:#x}", len, HEADER_SIZE)),
+    };
 
-    let body = data.split_off(HEADER_SIZE);
+    let mut reader = RomReader::new(Cursor::new(data))?;
+    let body = reader.read_range(0, body_len)?;
+    let (header, _) = reader.into_parts();
 
-    Ok(ROM {
-        header: InternalHeader::new(data)?,
-        data: body,
-    })
+    Ok(ROM { header, data: body })
 }
 
 #[cfg(test)]
@@ -207,11 +164,72 @@ mod tests {
 
             let rom = parse(bytes).expect("failed to parse");
             let header = rom.header;
-            assert_eq!(header.pi_bsb_dom1_lat_reg(), HEADER_NATIVE[0]);
-            assert_eq!(header.pi_bsd_dom1_pgs_reg(), HEADER_NATIVE[1]);
-            assert_eq!(header.pi_bsd_dom1_pwd_reg(), HEADER_NATIVE[2]);
+            assert_eq!(header.pi_bsb_dom1_lat_reg().unwrap(), HEADER_NATIVE[0]);
+            assert_eq!(header.pi_bsd_dom1_pgs_reg().unwrap(), HEADER_NATIVE[1]);
+            assert_eq!(header.pi_bsd_dom1_pwd_reg().unwrap(), HEADER_NATIVE[2]);
             // Some roms don't have the exact same bits here
             // assert_eq!(header.pi_bsb_dom1_pgs_reg(), HEADER_NATIVE[3]);
         }
     }
+
+    /// Builds a minimal native-endian ROM: a header carrying the native
+    /// magic and a deterministic body of `body_len` bytes.
+    fn build_native_rom(body_len: usize) -> Vec<u8> {
+        let mut data = vec![0u8; HEADER_SIZE];
+        data[0..4].copy_from_slice(&HEADER_NATIVE);
+        data.extend((0..body_len as u32).map(|i| (i % 256) as u8));
+        data
+    }
+
+    #[test]
+    fn test_to_vec_native_is_identity() {
+        let native = build_native_rom(64);
+        let rom = parse(native.clone()).unwrap();
+        assert_eq!(rom.to_vec(Endian::Native).unwrap(), native);
+    }
+
+    #[test]
+    fn test_write_to_vec_round_trip_little_endian() {
+        let native = build_native_rom(64);
+        let rom = parse(native).unwrap();
+
+        let little = rom.to_vec(Endian::Little).unwrap();
+        let round_tripped = parse(little).unwrap();
+
+        assert_eq!(round_tripped.data, rom.data);
+    }
+
+    #[test]
+    fn test_write_to_vec_round_trip_byte_swapped() {
+        let native = build_native_rom(64);
+        let rom = parse(native).unwrap();
+
+        let swapped = rom.to_vec(Endian::ByteSwapped).unwrap();
+        let round_tripped = parse(swapped).unwrap();
+
+        assert_eq!(round_tripped.data, rom.data);
+    }
+
+    #[test]
+    fn test_swap_words_reverses_each_four_byte_group() {
+        // This is exactly the bug the series fixed: the old code called
+        // `data.reverse()` on the whole buffer instead of per word.
+        let mut data = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        swap_words(&mut data);
+        assert_eq!(data, vec![4, 3, 2, 1, 8, 7, 6, 5]);
+    }
+
+    #[test]
+    fn test_swap_words_leaves_trailing_partial_group_untouched() {
+        let mut data = vec![1, 2, 3, 4, 5, 6];
+        swap_words(&mut data);
+        assert_eq!(data, vec![4, 3, 2, 1, 5, 6]);
+    }
+
+    #[test]
+    fn test_swap_bytes_pairwise_leaves_trailing_odd_byte_untouched() {
+        let mut data = vec![1, 2, 3];
+        swap_bytes_pairwise(&mut data);
+        assert_eq!(data, vec![2, 1, 3]);
+    }
 }